@@ -0,0 +1,402 @@
+use std::collections::HashSet;
+use std::io;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tracing::debug;
+
+use libp2p::core::upgrade::{read_length_prefixed, write_length_prefixed};
+use libp2p::core::{Endpoint, Multiaddr};
+use libp2p::identity::PeerId;
+use libp2p::request_response::{
+    self, Codec as RequestResponseCodec, Event as RequestResponseEvent,
+    Message as RequestResponseMessage, ProtocolSupport,
+};
+use libp2p::swarm::{
+    ConnectionDenied, ConnectionId, NetworkBehaviour, PollParameters, THandler, THandlerInEvent,
+    ToSwarm,
+};
+use libp2p::StreamProtocol;
+
+/// Maximum size in bytes of a single dispersed payload.
+pub const MAX_DISPERSAL_SIZE: usize = 4 * 1024 * 1024;
+
+const PROTOCOL_NAME: StreamProtocol = StreamProtocol::new("/ipfs/dispersal/1.0.0");
+
+/// A block, or a CID list, pushed directly to a peer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispersalPayload(pub Vec<u8>);
+
+/// Acknowledgement sent back once a dispersed payload has been received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispersalAck;
+
+// Single-byte marker written/read for `DispersalAck` so the ack actually round-trips over the
+// wire instead of the initiator's side resolving as soon as its own write completes.
+const ACK_MARKER: u8 = 0x01;
+
+#[derive(Debug, Clone, Default)]
+struct DispersalCodec;
+
+#[async_trait]
+impl RequestResponseCodec for DispersalCodec {
+    type Protocol = StreamProtocol;
+    type Request = DispersalPayload;
+    type Response = DispersalAck;
+
+    async fn read_request<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Request>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, MAX_DISPERSAL_SIZE).await?;
+        Ok(DispersalPayload(bytes))
+    }
+
+    async fn read_response<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+    ) -> io::Result<Self::Response>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let bytes = read_length_prefixed(io, 1).await?;
+        match bytes.as_slice() {
+            [ACK_MARKER] => Ok(DispersalAck),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed dispersal ack",
+            )),
+        }
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        DispersalPayload(bytes): Self::Request,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, &bytes).await?;
+        io.close().await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &Self::Protocol,
+        io: &mut T,
+        _: Self::Response,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_length_prefixed(io, [ACK_MARKER]).await?;
+        io.close().await
+    }
+}
+
+/// Events emitted by [`Dispersal`].
+#[derive(Debug)]
+pub enum DispersalEvent {
+    /// `payload` arrived from `from` over a freshly opened substream.
+    Received { from: PeerId, payload: Vec<u8> },
+    /// Pushing a payload to `to` failed, e.g. because no connection could be established.
+    SendFailed { to: PeerId, error: String },
+    /// A request-response event that doesn't map onto a dispersal concept exposed above (e.g. a
+    /// variant added to `request_response::Event` by a later libp2p version). Logged and
+    /// forwarded rather than dropped so upgrades don't silently lose events.
+    Unhandled,
+}
+
+/// Companion behaviour to [`crate::p2p::gossipsub::GossipsubStream`] that pushes content directly
+/// to a known set of peers over dedicated substreams, rather than flooding the gossipsub mesh.
+/// Intended for moving large blocks or CID lists to specific providers with predictable
+/// bandwidth, complementing pubsub's epidemic broadcast for control-plane messaging.
+pub struct Dispersal {
+    inner: request_response::Behaviour<DispersalCodec>,
+
+    // Peers we've already greeted via `on_peer_stream`, so the callback fires only once per peer
+    // while it stays connected.
+    known_peers: HashSet<PeerId>,
+
+    // Invoked the first time a connection to a new peer is established (and again when it
+    // reconnects after its last connection closed), so callers can start dispersing to it as
+    // soon as a stream to it becomes available.
+    on_peer_stream: Option<Arc<dyn Fn(PeerId) + Send + Sync>>,
+}
+
+impl core::ops::Deref for Dispersal {
+    type Target = request_response::Behaviour<DispersalCodec>;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+impl core::ops::DerefMut for Dispersal {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.inner
+    }
+}
+
+impl Default for Dispersal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Dispersal {
+    /// Constructs a new `Dispersal` behaviour with no peer-stream callback installed.
+    pub fn new() -> Self {
+        Dispersal {
+            inner: request_response::Behaviour::new(
+                DispersalCodec,
+                std::iter::once((PROTOCOL_NAME, ProtocolSupport::Full)),
+                request_response::Config::default(),
+            ),
+            known_peers: HashSet::new(),
+            on_peer_stream: None,
+        }
+    }
+
+    /// Installs `callback`, invoked the first time a connection to a new peer is established,
+    /// and again when that peer reconnects after its last connection closed.
+    pub fn with_peer_stream_callback<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(PeerId) + Send + Sync + 'static,
+    {
+        self.on_peer_stream = Some(Arc::new(callback));
+        self
+    }
+
+    // Records a connection to `peer_id`, invoking `on_peer_stream` the first time this peer is
+    // seen (or the first time since it was last forgotten via `note_peer_disconnected`).
+    fn note_peer_connected(&mut self, peer_id: PeerId) {
+        if self.known_peers.insert(peer_id) {
+            if let Some(callback) = self.on_peer_stream.as_ref() {
+                callback(peer_id);
+            }
+        }
+    }
+
+    // Forgets `peer_id`, so a later reconnection is treated as new and re-fires
+    // `on_peer_stream`.
+    fn note_peer_disconnected(&mut self, peer_id: &PeerId) {
+        self.known_peers.remove(peer_id);
+    }
+
+    /// Pushes `payload` directly to every peer in `targets`, opening a dedicated outbound
+    /// substream per peer. Delivery is fire-and-forget from the caller's perspective; failures
+    /// surface as [`DispersalEvent::SendFailed`] through the event stream.
+    pub fn disperse(
+        &mut self,
+        targets: impl IntoIterator<Item = PeerId>,
+        payload: impl Into<Vec<u8>>,
+    ) {
+        let payload = payload.into();
+        for peer in targets {
+            self.inner
+                .send_request(&peer, DispersalPayload(payload.clone()));
+        }
+    }
+}
+
+impl NetworkBehaviour for Dispersal {
+    type ConnectionHandler = <request_response::Behaviour<DispersalCodec> as NetworkBehaviour>::ConnectionHandler;
+    type ToSwarm = DispersalEvent;
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        addresses: &[Multiaddr],
+        effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        self.inner.handle_pending_outbound_connection(
+            connection_id,
+            maybe_peer,
+            addresses,
+            effective_role,
+        )
+    }
+
+    fn on_swarm_event(&mut self, event: libp2p::swarm::FromSwarm<Self::ConnectionHandler>) {
+        match &event {
+            libp2p::swarm::FromSwarm::ConnectionEstablished(established) => {
+                self.note_peer_connected(established.peer_id);
+            }
+            // Drop the peer from `known_peers` once its last connection closes, symmetric with
+            // `GossipsubStream`'s cleanup of its own per-peer state: without this, a peer that
+            // disconnects and later reconnects would never have `on_peer_stream` fire again for
+            // it, and `known_peers` would grow unboundedly over a long-running node's peer churn.
+            libp2p::swarm::FromSwarm::ConnectionClosed(closed) if closed.remaining_established == 0 => {
+                self.note_peer_disconnected(&closed.peer_id);
+            }
+            _ => {}
+        }
+        self.inner.on_swarm_event(event);
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: libp2p::swarm::ConnectionId,
+        event: libp2p::swarm::THandlerOutEvent<Self>,
+    ) {
+        self.inner
+            .on_connection_handler_event(peer_id, connection_id, event)
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_inbound_connection(
+            connection_id,
+            peer,
+            local_addr,
+            remote_addr,
+        )
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        role_override: Endpoint,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.inner.handle_established_outbound_connection(
+            connection_id,
+            peer,
+            addr,
+            role_override,
+        )
+    }
+
+    fn poll(
+        &mut self,
+        ctx: &mut Context,
+        poll: &mut impl PollParameters,
+    ) -> Poll<ToSwarm<DispersalEvent, THandlerInEvent<Self>>> {
+        loop {
+            match futures::ready!(self.inner.poll(ctx, poll)) {
+                ToSwarm::GenerateEvent(RequestResponseEvent::Message { peer, message }) => {
+                    match message {
+                        RequestResponseMessage::Request {
+                            request, channel, ..
+                        } => {
+                            let DispersalPayload(payload) = request;
+                            if self.inner.send_response(channel, DispersalAck).is_err() {
+                                debug!("failed to ack dispersal from {:?}", peer);
+                            }
+                            return Poll::Ready(ToSwarm::GenerateEvent(
+                                DispersalEvent::Received {
+                                    from: peer,
+                                    payload,
+                                },
+                            ));
+                        }
+                        RequestResponseMessage::Response { .. } => continue,
+                    }
+                }
+                ToSwarm::GenerateEvent(RequestResponseEvent::OutboundFailure {
+                    peer,
+                    error,
+                    ..
+                }) => {
+                    return Poll::Ready(ToSwarm::GenerateEvent(DispersalEvent::SendFailed {
+                        to: peer,
+                        error: error.to_string(),
+                    }));
+                }
+                ToSwarm::GenerateEvent(RequestResponseEvent::InboundFailure { .. })
+                | ToSwarm::GenerateEvent(RequestResponseEvent::ResponseSent { .. }) => continue,
+                // `request_response::Event` is `#[non_exhaustive]`, so this also catches any
+                // variant not explicitly matched above (present or future); map it to
+                // `Unhandled` rather than asserting unreachable so an upstream libp2p bump can't
+                // turn an ignored event into a panic.
+                action => {
+                    return Poll::Ready(action.map_out(|event| {
+                        debug!("unhandled request-response event: {:?}", event);
+                        DispersalEvent::Unhandled
+                    }));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+    use std::sync::Mutex;
+
+    #[test]
+    fn codec_round_trips_request_and_response() {
+        let mut codec = DispersalCodec;
+        let protocol = PROTOCOL_NAME;
+
+        let mut request_io = Cursor::new(Vec::new());
+        futures::executor::block_on(codec.write_request(
+            &protocol,
+            &mut request_io,
+            DispersalPayload(b"hello".to_vec()),
+        ))
+        .unwrap();
+        let mut request_io = Cursor::new(request_io.into_inner());
+        let decoded =
+            futures::executor::block_on(codec.read_request(&protocol, &mut request_io)).unwrap();
+        assert_eq!(decoded, DispersalPayload(b"hello".to_vec()));
+
+        let mut response_io = Cursor::new(Vec::new());
+        futures::executor::block_on(codec.write_response(&protocol, &mut response_io, DispersalAck))
+            .unwrap();
+        let mut response_io = Cursor::new(response_io.into_inner());
+        let decoded =
+            futures::executor::block_on(codec.read_response(&protocol, &mut response_io)).unwrap();
+        assert_eq!(decoded, DispersalAck);
+    }
+
+    #[test]
+    fn read_response_rejects_a_malformed_ack() {
+        let mut codec = DispersalCodec;
+        let mut response_io = Cursor::new(Vec::new());
+
+        let result =
+            futures::executor::block_on(codec.read_response(&PROTOCOL_NAME, &mut response_io));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn peer_stream_callback_fires_once_until_peer_disconnects() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let calls_clone = calls.clone();
+        let mut dispersal =
+            Dispersal::new().with_peer_stream_callback(move |peer| calls_clone.lock().unwrap().push(peer));
+        let peer_id = PeerId::random();
+
+        dispersal.note_peer_connected(peer_id);
+        // A second connection to the same, still-known peer doesn't re-fire the callback.
+        dispersal.note_peer_connected(peer_id);
+        assert_eq!(calls.lock().unwrap().as_slice(), &[peer_id]);
+
+        // Once the peer is forgotten (its last connection closed), a later reconnection is
+        // treated as new again.
+        dispersal.note_peer_disconnected(&peer_id);
+        dispersal.note_peer_connected(peer_id);
+        assert_eq!(calls.lock().unwrap().as_slice(), &[peer_id, peer_id]);
+    }
+}
@@ -1,7 +1,7 @@
 use futures::channel::mpsc::{self as channel};
 use futures::stream::{FusedStream, Stream};
 use libp2p::gossipsub::PublishError;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::pin::Pin;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -14,13 +14,187 @@ use libp2p::identity::PeerId;
 
 use libp2p::gossipsub::{
     Behaviour as Gossipsub, Event as GossipsubEvent, IdentTopic as Topic,
-    Message as GossipsubMessage, MessageId, TopicHash,
+    Message as GossipsubMessage, MessageId, PeerScoreParams, PeerScoreThresholds, TopicHash,
 };
 use libp2p::swarm::{
     ConnectionDenied, ConnectionId, NetworkBehaviour, PollParameters, THandler, THandlerInEvent,
     ToSwarm,
 };
 
+/// Events emitted by [`GossipsubStream`]. Wraps the events forwarded straight from gossipsub and
+/// adds score-driven notifications computed by this wrapper.
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// An event forwarded unchanged from the underlying gossipsub behaviour.
+    Gossipsub(GossipsubEvent),
+    /// `peer_id`'s score dropped below `threshold`, having previously been above it.
+    PeerScoreBelowThreshold {
+        peer_id: PeerId,
+        score: f64,
+        threshold: ScoreThreshold,
+    },
+}
+
+/// Which configured score threshold a [`Event::PeerScoreBelowThreshold`] crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoreThreshold {
+    /// Below this, gossip (IHAVE/IWANT) with the peer is stopped.
+    Gossip,
+    /// Below this, the peer is excluded when publishing.
+    Publish,
+    /// Below this, the peer is graylisted: all RPCs to and from it are ignored.
+    Graylist,
+}
+
+// Coarse band a peer's score falls into relative to the configured thresholds, ordered from
+// least to most severe so transitions to a worse band can be detected with a simple comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum ScoreBand {
+    Normal,
+    BelowGossip,
+    BelowPublish,
+    BelowGraylist,
+}
+
+// Generates a fresh 32-byte salt; called once per `GossipsubStream::with_salted_content_ids`
+// call.
+fn random_salt() -> [u8; 32] {
+    use rand::RngCore;
+
+    let mut salt = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut salt);
+    salt
+}
+
+// Derives a `MessageId` from `message`'s content and topic, hashed with a SipHash instance keyed
+// by `salt`. `DefaultHasher` always starts from the same fixed, public SipHash keys, so hashing
+// the salt as ordinary input data (rather than as the key) would not have made ids unpredictable
+// to a peer willing to brute-force or precompute against those known keys; keying the hasher
+// itself closes that gap.
+fn salted_message_id(salt: [u8; 32], message: &GossipsubMessage) -> MessageId {
+    use siphasher::sip::SipHasher13;
+    use std::hash::{Hash, Hasher};
+
+    let mut quarters = salt
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("chunk is 8 bytes")));
+    let (q0, q1, q2, q3) = (
+        quarters.next().expect("salt is 32 bytes"),
+        quarters.next().expect("salt is 32 bytes"),
+        quarters.next().expect("salt is 32 bytes"),
+        quarters.next().expect("salt is 32 bytes"),
+    );
+
+    let mut hasher = SipHasher13::new_with_keys(q0 ^ q2, q1 ^ q3);
+    message.data.hash(&mut hasher);
+    message.topic.hash(&mut hasher);
+    MessageId::from(hasher.finish().to_be_bytes().to_vec())
+}
+
+// Sums a peer's externally-set application score and its accumulated subscription-filter
+// penalty, the single value gossipsub's `set_application_score` accepts. Pulled out of
+// `GossipsubStream::apply_combined_application_score` so the composition itself is testable
+// without a live `Gossipsub` instance.
+fn combined_application_score(
+    external_application_scores: &HashMap<PeerId, f64>,
+    subscription_penalties: &HashMap<PeerId, f64>,
+    peer_id: &PeerId,
+) -> f64 {
+    let external = external_application_scores
+        .get(peer_id)
+        .copied()
+        .unwrap_or(0.0);
+    let penalty = subscription_penalties
+        .get(peer_id)
+        .copied()
+        .unwrap_or(0.0);
+    external + penalty
+}
+
+// Classifies `score` into a `ScoreBand` relative to `thresholds` and, if that's strictly worse
+// than the band previously recorded for `peer_id` in `score_bands`, records the new band and
+// returns the crossed threshold. Returns `None` (recording the new band regardless) when the
+// band hasn't worsened, which is what keeps repeated calls for a peer sitting still from
+// re-emitting the same crossing. Pulled out of `GossipsubStream::next_score_transition` so the
+// transition logic is testable without a live `Gossipsub` instance.
+fn record_score_band(
+    score_bands: &mut HashMap<PeerId, ScoreBand>,
+    thresholds: &PeerScoreThresholds,
+    peer_id: PeerId,
+    score: f64,
+) -> Option<(PeerId, f64, ScoreThreshold)> {
+    let band = if score < thresholds.graylist_threshold {
+        ScoreBand::BelowGraylist
+    } else if score < thresholds.publish_threshold {
+        ScoreBand::BelowPublish
+    } else if score < thresholds.gossip_threshold {
+        ScoreBand::BelowGossip
+    } else {
+        ScoreBand::Normal
+    };
+
+    let previous = score_bands.entry(peer_id).or_insert(ScoreBand::Normal);
+    let transitioned = band > *previous;
+    *previous = band;
+
+    if transitioned {
+        let threshold = match band {
+            ScoreBand::BelowGraylist => ScoreThreshold::Graylist,
+            ScoreBand::BelowPublish => ScoreThreshold::Publish,
+            ScoreBand::BelowGossip => ScoreThreshold::Gossip,
+            ScoreBand::Normal => unreachable!("band > previous implies worse than Normal"),
+        };
+        Some((peer_id, score, threshold))
+    } else {
+        None
+    }
+}
+
+/// Default number of messages retained per topic before the [`BacklogPolicy`] kicks in.
+pub const DEFAULT_MAX_BACKLOG: usize = 64;
+
+/// What to do with an incoming message for a topic whose backlog is already at `max_backlog`.
+///
+/// Defaults to [`BacklogPolicy::DropOldest`], which never stalls draining: `Block` is a
+/// deliberate opt-in via [`GossipsubStream::new`], since [`From<Gossipsub>`] is an existing,
+/// widely-used conversion and silently defaulting it to a policy that can stall gossipsub's
+/// `poll` would be a behavior change callers didn't ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BacklogPolicy {
+    /// Evict the oldest queued message to make room for the new one.
+    DropOldest,
+    /// Discard the newly arrived message, leaving the existing backlog untouched.
+    DropNewest,
+    /// Keep every message. Once this topic's backlog is full and none of its subscribers can
+    /// take a message, `poll` returns `Pending` without polling gossipsub at all, which stalls
+    /// messages for every topic and gossipsub's other events alike until a subscriber catches
+    /// up and frees up capacity. This is what lets slow subscribers propagate backpressure to
+    /// the swarm instead of the backlog growing unbounded.
+    Block,
+}
+
+impl Default for BacklogPolicy {
+    fn default() -> Self {
+        BacklogPolicy::DropOldest
+    }
+}
+
+/// Per-topic backlog counters, useful for observing whether subscribers are keeping up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BacklogStats {
+    /// Total number of messages accepted into the backlog.
+    pub queued: u64,
+    /// Total number of messages dropped because the backlog was full.
+    pub dropped: u64,
+}
+
+// Bounded per-topic backlog of messages awaiting delivery to subscribers.
+#[derive(Default)]
+struct TopicBacklog {
+    messages: VecDeque<GossipsubMessage>,
+    stats: BacklogStats,
+}
+
 /// Currently a thin wrapper around Gossipsub.
 /// Allows single subscription to a topic with only unbounded senders. Tracks the peers subscribed
 /// to different topics.
@@ -40,10 +214,45 @@ pub struct GossipsubStream {
         channel::UnboundedReceiver<TopicHash>,
     ),
 
-    // Backlog of messages received for a given topic
-    queue_messages: HashMap<TopicHash, VecDeque<GossipsubMessage>>,
+    // Backlog of messages received for a given topic, bounded by `max_backlog` and trimmed
+    // according to `backlog_policy`.
+    queue_messages: HashMap<TopicHash, TopicBacklog>,
+
+    // Maximum number of messages retained per topic before `backlog_policy` applies.
+    max_backlog: usize,
+
+    // What to do with a topic's backlog once it reaches `max_backlog`.
+    backlog_policy: BacklogPolicy,
+
+    // Set once `with_peer_score` installs scoring; used to detect threshold crossings in `poll`.
+    peer_score_thresholds: Option<PeerScoreThresholds>,
+
+    // Last observed score band per peer, used to only emit an event on a transition to a worse
+    // band rather than on every poll.
+    score_bands: HashMap<PeerId, ScoreBand>,
+
+    // User-supplied validator run against incoming SUBSCRIBE announcements, plus the application
+    // score penalty applied to a peer each time it fails the check.
+    subscription_filter: Option<(SubscriptionFilter, f64)>,
+
+    // Subscriptions rejected by `subscription_filter`, excluded from `subscribed_peers`.
+    rejected_subscriptions: HashSet<(PeerId, TopicHash)>,
+
+    // Accumulated application-score penalty per peer from failing `subscription_filter`, so
+    // repeat offenders get throttled further each time. Kept separate from
+    // `external_application_scores` so the two features compose additively instead of one
+    // silently overwriting the other through gossipsub's single application-score slot.
+    subscription_penalties: HashMap<PeerId, f64>,
+
+    // Last score an external caller passed to `Self::set_application_score`, so it can be
+    // recombined with `subscription_penalties` whenever either changes.
+    external_application_scores: HashMap<PeerId, f64>,
 }
 
+// A user-supplied callback deciding whether a remote peer's SUBSCRIBE announcement should be
+// honoured. See [`GossipsubStream::with_subscription_filter`].
+type SubscriptionFilter = Arc<dyn Fn(&PeerId, &TopicHash) -> bool + Send + Sync>;
+
 impl core::ops::Deref for GossipsubStream {
     type Target = Gossipsub;
     fn deref(&self) -> &Self::Target {
@@ -126,6 +335,14 @@ impl FusedStream for SubscriptionStream {
 
 impl From<Gossipsub> for GossipsubStream {
     fn from(gossipsub: Gossipsub) -> Self {
+        GossipsubStream::new(gossipsub, DEFAULT_MAX_BACKLOG, BacklogPolicy::default())
+    }
+}
+
+impl GossipsubStream {
+    /// Constructs a new `GossipsubStream`, bounding each topic's message backlog to
+    /// `max_backlog` entries and applying `backlog_policy` once that bound is reached.
+    pub fn new(gossipsub: Gossipsub, max_backlog: usize, backlog_policy: BacklogPolicy) -> Self {
         let (tx, rx) = channel::unbounded();
         GossipsubStream {
             streams: HashMap::new(),
@@ -133,11 +350,67 @@ impl From<Gossipsub> for GossipsubStream {
             unsubscriptions: (tx, rx),
             active_streams: Default::default(),
             queue_messages: Default::default(),
+            max_backlog,
+            backlog_policy,
+            peer_score_thresholds: None,
+            score_bands: Default::default(),
+            subscription_filter: None,
+            rejected_subscriptions: Default::default(),
+            subscription_penalties: Default::default(),
+            external_application_scores: Default::default(),
         }
     }
-}
 
-impl GossipsubStream {
+    /// Validates remote peers' SUBSCRIBE announcements with `filter`. When `filter` returns
+    /// `false` for a `(peer_id, topic)` pair, `poll` suppresses the corresponding
+    /// `Event::Gossipsub(GossipsubEvent::Subscribed)`, the peer is excluded from
+    /// [`Self::subscribed_peers`] for that topic, and `penalty` is subtracted from the peer's
+    /// application score (accumulating on repeat offenses) so persistent offenders get
+    /// throttled via gossipsub's own scoring.
+    pub fn with_subscription_filter<F>(mut self, filter: F, penalty: f64) -> Self
+    where
+        F: Fn(&PeerId, &TopicHash) -> bool + Send + Sync + 'static,
+    {
+        self.subscription_filter = Some((Arc::new(filter), penalty));
+        self
+    }
+
+    /// Installs gossipsub's peer scoring with `params` and `thresholds`. Once set,
+    /// [`GossipsubStream::poll`] emits [`Event::PeerScoreBelowThreshold`] whenever a peer's score
+    /// drops below one of `thresholds` having previously been above it, and [`Self::peer_score`]
+    /// starts returning scores instead of `None`.
+    pub fn with_peer_score(
+        mut self,
+        params: PeerScoreParams,
+        thresholds: PeerScoreThresholds,
+    ) -> Result<Self, String> {
+        self.gossipsub
+            .with_peer_score(params, thresholds.clone())?;
+        self.peer_score_thresholds = Some(thresholds);
+        Ok(self)
+    }
+
+    /// Builds a `GossipsubStream` whose message ids are derived from message content hashed
+    /// together with a random salt generated afresh by this constructor, instead of gossipsub's
+    /// default author+sequence-number id. This makes ids unpredictable to remote peers, so an
+    /// attacker cannot pre-seed or collide entries in the seen-message cache to suppress
+    /// legitimate messages; identical payloads are still deduplicated locally. Ids produced this
+    /// way are node-local: they are not comparable across nodes, or across restarts of this node.
+    pub fn with_salted_content_ids(
+        authenticity: libp2p::gossipsub::MessageAuthenticity,
+        config_builder: libp2p::gossipsub::ConfigBuilder,
+        max_backlog: usize,
+        backlog_policy: BacklogPolicy,
+    ) -> Result<Self, String> {
+        let salt = random_salt();
+        let config = config_builder
+            .message_id_fn(move |message| salted_message_id(salt, message))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let gossipsub = Gossipsub::new(authenticity, config)?;
+        Ok(Self::new(gossipsub, max_backlog, backlog_policy))
+    }
+
     /// Subscribes to a currently unsubscribed topic.
     /// Returns a receiver for messages sent to the topic or `None` if subscription existed
     /// already.
@@ -220,12 +493,18 @@ impl GossipsubStream {
         self.all_peers().map(|(peer, _)| *peer).collect()
     }
 
-    /// Returns the peers known to subscribe to the given topic
+    /// Returns the peers known to subscribe to the given topic. Peers whose subscription to this
+    /// topic was rejected by the [`Self::with_subscription_filter`] callback are excluded.
     pub fn subscribed_peers(&self, topic: &str) -> Vec<PeerId> {
         let topic = Topic::new(topic);
         self.all_peers()
             .filter(|(_, list)| list.contains(&&topic.hash()))
             .map(|(peer_id, _)| *peer_id)
+            .filter(|peer_id| {
+                !self
+                    .rejected_subscriptions
+                    .contains(&(*peer_id, topic.hash()))
+            })
             .collect()
     }
 
@@ -234,11 +513,102 @@ impl GossipsubStream {
     pub fn subscribed_topics(&self) -> Vec<String> {
         self.streams.keys().map(|t| t.to_string()).collect()
     }
+
+    /// Returns the queued/dropped counters for `topic`, or `None` if no message has been
+    /// backlogged for it since it was subscribed.
+    pub fn backlog_stats(&self, topic: &str) -> Option<BacklogStats> {
+        let topic = Topic::new(topic);
+        self.queue_messages.get(&topic.hash()).map(|b| b.stats)
+    }
+
+    /// Returns `peer_id`'s current gossipsub score, or `None` if scoring hasn't been installed
+    /// via [`Self::with_peer_score`] or the peer is unknown.
+    pub fn peer_score(&self, peer_id: &PeerId) -> Option<f64> {
+        self.gossipsub.peer_score(peer_id)
+    }
+
+    /// Applies an application-level score adjustment to `peer_id`, on top of whatever gossipsub's
+    /// own scoring has computed for it. Composes with any penalty accrued through
+    /// [`Self::with_subscription_filter`] instead of overwriting it. Returns `false` if scoring
+    /// hasn't been installed.
+    pub fn set_application_score(&mut self, peer_id: &PeerId, score: f64) -> bool {
+        self.external_application_scores.insert(*peer_id, score);
+        self.apply_combined_application_score(*peer_id)
+    }
+
+    // gossipsub only exposes a single application-score slot per peer, but `set_application_score`
+    // (an external, absolute score) and the subscription-filter penalty (an internal, additive
+    // term) both need to land there without one clobbering the other. This recombines the two
+    // and writes the sum, called whenever either input changes.
+    fn apply_combined_application_score(&mut self, peer_id: PeerId) -> bool {
+        let score = combined_application_score(
+            &self.external_application_scores,
+            &self.subscription_penalties,
+            &peer_id,
+        );
+        self.gossipsub.set_application_score(&peer_id, score)
+    }
+
+    // Scans known peers for a score that has newly dropped below one of the configured
+    // thresholds, returning at most one transition per call so `poll` can drain them one at a
+    // time like its other events.
+    fn next_score_transition(&mut self) -> Option<(PeerId, f64, ScoreThreshold)> {
+        let thresholds = self.peer_score_thresholds.clone()?;
+
+        for peer_id in self.known_peers() {
+            let Some(score) = self.gossipsub.peer_score(&peer_id) else {
+                continue;
+            };
+
+            if let Some(transition) =
+                record_score_band(&mut self.score_bands, &thresholds, peer_id, score)
+            {
+                return Some(transition);
+            }
+        }
+
+        None
+    }
+
+    // Enqueues `message` onto `topic`'s backlog, applying `backlog_policy` once `max_backlog`
+    // has been reached.
+    fn enqueue_message(&mut self, topic: TopicHash, message: GossipsubMessage) {
+        let max_backlog = self.max_backlog;
+        let backlog_policy = self.backlog_policy;
+        let backlog = self.queue_messages.entry(topic).or_default();
+
+        if backlog.messages.len() < max_backlog {
+            backlog.messages.push_back(message);
+            backlog.stats.queued += 1;
+            return;
+        }
+
+        match backlog_policy {
+            BacklogPolicy::DropOldest => {
+                backlog.messages.pop_front();
+                backlog.messages.push_back(message);
+                backlog.stats.queued += 1;
+                backlog.stats.dropped += 1;
+            }
+            BacklogPolicy::DropNewest => {
+                backlog.stats.dropped += 1;
+            }
+            BacklogPolicy::Block => {
+                // `poll` returns `Pending` before draining gossipsub once a subscribed topic's
+                // `Block` backlog is full with no ready sender, so this is only reached when
+                // there was nothing to stall on: a `max_backlog` of 0, or a topic with no
+                // subscription streams. The message is kept past the bound rather than
+                // dropped, preserving the policy's "never drop" guarantee.
+                backlog.messages.push_back(message);
+                backlog.stats.queued += 1;
+            }
+        }
+    }
 }
 
 impl NetworkBehaviour for GossipsubStream {
     type ConnectionHandler = <Gossipsub as NetworkBehaviour>::ConnectionHandler;
-    type ToSwarm = GossipsubEvent;
+    type ToSwarm = Event;
 
     fn handle_pending_outbound_connection(
         &mut self,
@@ -256,6 +626,19 @@ impl NetworkBehaviour for GossipsubStream {
     }
 
     fn on_swarm_event(&mut self, event: libp2p::swarm::FromSwarm<Self::ConnectionHandler>) {
+        if let libp2p::swarm::FromSwarm::ConnectionClosed(closed) = &event {
+            if closed.remaining_established == 0 {
+                // Last connection to this peer gone: drop its rejected-subscription entries and
+                // accumulated penalty rather than letting them linger for a peer we may never
+                // see again.
+                let peer_id = closed.peer_id;
+                self.rejected_subscriptions
+                    .retain(|(peer, _)| *peer != peer_id);
+                self.subscription_penalties.remove(&peer_id);
+                self.external_application_scores.remove(&peer_id);
+                self.score_bands.remove(&peer_id);
+            }
+        }
         self.gossipsub.on_swarm_event(event)
     }
 
@@ -303,7 +686,7 @@ impl NetworkBehaviour for GossipsubStream {
         &mut self,
         ctx: &mut Context,
         poll: &mut impl PollParameters,
-    ) -> Poll<ToSwarm<libp2p::gossipsub::Event, THandlerInEvent<Self>>> {
+    ) -> Poll<ToSwarm<Event, THandlerInEvent<Self>>> {
         use futures::stream::StreamExt;
         use std::collections::hash_map::Entry;
 
@@ -332,10 +715,25 @@ impl NetworkBehaviour for GossipsubStream {
         }
 
         loop {
+            if let Some((peer_id, score, threshold)) = self.next_score_transition() {
+                return Poll::Ready(ToSwarm::GenerateEvent(Event::PeerScoreBelowThreshold {
+                    peer_id,
+                    score,
+                    threshold,
+                }));
+            }
+
+            // Set once a `Block`-policy topic is full and every one of its senders is still
+            // `Poll::Pending`; this stops us from draining further messages out of gossipsub
+            // this round so the resulting backpressure can propagate to the publisher.
+            let mut blocked = false;
+
             if !self.queue_messages.is_empty() {
-                self.queue_messages.retain(|topic, list| {
-                    if list.is_empty() {
-                        return false;
+                self.queue_messages.retain(|topic, backlog| {
+                    if backlog.messages.is_empty() {
+                        // Keep a drained backlog (and its counters) while the topic is still
+                        // subscribed, so `backlog_stats` keeps reporting totals.
+                        return self.streams.contains_key(topic);
                     }
 
                     if let Entry::Occupied(mut oe) = self.streams.entry(topic.clone()) {
@@ -357,12 +755,14 @@ impl NetworkBehaviour for GossipsubStream {
                         }
 
                         let mut current_message = None;
+                        let mut any_ready = false;
 
                         for sender in senders {
                             match sender.poll_ready(ctx) {
                                 Poll::Ready(Ok(_)) => {
+                                    any_ready = true;
                                     if current_message.is_none() {
-                                        let Some(message) = list.pop_front() else {
+                                        let Some(message) = backlog.messages.pop_front() else {
                                             break;
                                         };
 
@@ -377,36 +777,291 @@ impl NetworkBehaviour for GossipsubStream {
                                 Poll::Pending => {}
                             }
                         }
+
+                        if !any_ready
+                            && self.backlog_policy == BacklogPolicy::Block
+                            && backlog.messages.len() >= self.max_backlog
+                        {
+                            blocked = true;
+                        }
                     }
                     true
                 });
             }
 
+            if blocked {
+                return Poll::Pending;
+            }
+
             match futures::ready!(self.gossipsub.poll(ctx, poll)) {
                 ToSwarm::GenerateEvent(GossipsubEvent::Message { message, .. }) => {
                     let topic = message.topic.clone();
-                    self.queue_messages
-                        .entry(topic)
-                        .or_default()
-                        .push_back(message);
+                    self.enqueue_message(topic, message);
                     continue;
                 }
                 ToSwarm::GenerateEvent(GossipsubEvent::Subscribed { peer_id, topic }) => {
-                    return Poll::Ready(ToSwarm::GenerateEvent(GossipsubEvent::Subscribed {
-                        peer_id,
-                        topic,
-                    }));
+                    if let Some((filter, penalty)) = self.subscription_filter.clone() {
+                        if !filter(&peer_id, &topic) {
+                            debug!(
+                                "rejecting subscription from {:?} to {:?}",
+                                peer_id, topic
+                            );
+                            self.rejected_subscriptions
+                                .insert((peer_id, topic.clone()));
+                            *self
+                                .subscription_penalties
+                                .entry(peer_id)
+                                .or_insert(0.0) -= penalty.abs();
+                            self.apply_combined_application_score(peer_id);
+                            continue;
+                        }
+                    }
+                    return Poll::Ready(ToSwarm::GenerateEvent(Event::Gossipsub(
+                        GossipsubEvent::Subscribed { peer_id, topic },
+                    )));
                 }
                 ToSwarm::GenerateEvent(GossipsubEvent::Unsubscribed { peer_id, topic }) => {
-                    return Poll::Ready(ToSwarm::GenerateEvent(GossipsubEvent::Unsubscribed {
-                        peer_id,
-                        topic,
-                    }));
+                    // The rejection, if any, was specific to this topic; let the peer be
+                    // re-evaluated by the filter if it subscribes again. The accumulated
+                    // application-score penalty is peer-wide and is left alone here, only
+                    // cleared on full disconnect (see `on_swarm_event`).
+                    self.rejected_subscriptions.remove(&(peer_id, topic.clone()));
+                    return Poll::Ready(ToSwarm::GenerateEvent(Event::Gossipsub(
+                        GossipsubEvent::Unsubscribed { peer_id, topic },
+                    )));
                 }
                 action => {
-                    return Poll::Ready(action);
+                    return Poll::Ready(action.map_out(Event::Gossipsub));
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::gossipsub::{Config, MessageAuthenticity};
+
+    fn test_stream(max_backlog: usize, policy: BacklogPolicy) -> GossipsubStream {
+        let gossipsub = Gossipsub::new(MessageAuthenticity::Anonymous, Config::default())
+            .expect("default config is valid");
+        GossipsubStream::new(gossipsub, max_backlog, policy)
+    }
+
+    fn message(topic: &TopicHash, data: &[u8]) -> GossipsubMessage {
+        GossipsubMessage {
+            source: None,
+            data: data.to_vec(),
+            sequence_number: None,
+            topic: topic.clone(),
+        }
+    }
+
+    fn backlog_contents(stream: &GossipsubStream, topic: &TopicHash) -> Vec<Vec<u8>> {
+        stream
+            .queue_messages
+            .get(topic)
+            .map(|backlog| backlog.messages.iter().map(|m| m.data.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn drop_oldest_evicts_the_first_message_past_capacity() {
+        let mut stream = test_stream(2, BacklogPolicy::DropOldest);
+        let topic = Topic::new("topic").hash();
+
+        stream.enqueue_message(topic.clone(), message(&topic, b"a"));
+        stream.enqueue_message(topic.clone(), message(&topic, b"b"));
+        stream.enqueue_message(topic.clone(), message(&topic, b"c"));
+
+        assert_eq!(
+            backlog_contents(&stream, &topic),
+            vec![b"b".to_vec(), b"c".to_vec()]
+        );
+
+        let stats = stream.backlog_stats("topic").unwrap();
+        assert_eq!(stats.queued, 3);
+        assert_eq!(stats.dropped, 1);
+    }
+
+    #[test]
+    fn drop_newest_discards_the_incoming_message_past_capacity() {
+        let mut stream = test_stream(2, BacklogPolicy::DropNewest);
+        let topic = Topic::new("topic").hash();
+
+        stream.enqueue_message(topic.clone(), message(&topic, b"a"));
+        stream.enqueue_message(topic.clone(), message(&topic, b"b"));
+        stream.enqueue_message(topic.clone(), message(&topic, b"c"));
+
+        assert_eq!(
+            backlog_contents(&stream, &topic),
+            vec![b"a".to_vec(), b"b".to_vec()]
+        );
+
+        let stats = stream.backlog_stats("topic").unwrap();
+        assert_eq!(stats.queued, 2);
+        assert_eq!(stats.dropped, 1);
+    }
+
+    // Stand-in for `PollParameters` sufficient to drive `GossipsubStream::poll` directly.
+    struct NoopPollParameters;
+
+    impl PollParameters for NoopPollParameters {
+        type SupportedProtocolsIter = std::iter::Empty<Vec<u8>>;
+        type ListenedAddressesIter = std::iter::Empty<Multiaddr>;
+        type ExternalAddressesIter = std::iter::Empty<libp2p::swarm::AddressRecord>;
+
+        fn supported_protocols(&self) -> Self::SupportedProtocolsIter {
+            std::iter::empty()
+        }
+
+        fn listened_addresses(&self) -> Self::ListenedAddressesIter {
+            std::iter::empty()
+        }
+
+        fn external_addresses(&self) -> Self::ExternalAddressesIter {
+            std::iter::empty()
+        }
+
+        fn local_peer_id(&self) -> &PeerId {
+            use std::sync::OnceLock;
+            static PEER_ID: OnceLock<PeerId> = OnceLock::new();
+            PEER_ID.get_or_init(PeerId::random)
+        }
+    }
+
+    // Records whether it was woken, so tests can assert a stalled `poll` actually gets woken
+    // back up once the condition it's waiting on changes.
+    struct FlagWaker(Arc<std::sync::atomic::AtomicBool>);
+
+    impl std::task::Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn block_policy_stalls_poll_until_a_subscriber_drains() {
+        use std::sync::atomic::AtomicBool;
+        use std::task::Waker;
+
+        let mut stream = test_stream(1, BacklogPolicy::Block);
+        let mut sub = stream.subscribe("topic").expect("subscribe");
+        let topic = Topic::new("topic").hash();
+
+        let woken = Arc::new(AtomicBool::new(false));
+        let waker = Waker::from(Arc::new(FlagWaker(woken.clone())));
+        let mut ctx = Context::from_waker(&waker);
+        let mut params = NoopPollParameters;
+
+        // First two messages: the subscriber's `channel(1)` holds two messages (one buffered
+        // slot plus the sender's own guaranteed slot), so each round's poll drains them
+        // straight through.
+        stream.enqueue_message(topic.clone(), message(&topic, b"a"));
+        assert!(stream.poll(&mut ctx, &mut params).is_pending());
+        assert!(backlog_contents(&stream, &topic).is_empty());
+        stream.enqueue_message(topic.clone(), message(&topic, b"b"));
+        assert!(stream.poll(&mut ctx, &mut params).is_pending());
+        assert!(backlog_contents(&stream, &topic).is_empty());
+
+        // Third message: the subscriber hasn't read the first two yet, so its channel is full
+        // and `poll` must stall rather than drop it.
+        stream.enqueue_message(topic.clone(), message(&topic, b"c"));
+        assert!(stream.poll(&mut ctx, &mut params).is_pending());
+        assert_eq!(backlog_contents(&stream, &topic), vec![b"c".to_vec()]);
+        assert!(!woken.swap(false, Ordering::SeqCst));
+
+        // Draining the subscriber frees capacity, which should wake the registered context.
+        futures::executor::block_on(futures::stream::StreamExt::next(&mut sub));
+        assert!(woken.load(Ordering::SeqCst));
+
+        // The freed capacity lets the still-queued message through on the next poll.
+        assert!(stream.poll(&mut ctx, &mut params).is_pending());
+        assert!(backlog_contents(&stream, &topic).is_empty());
+    }
+
+    #[test]
+    fn backlog_stats_is_none_for_a_topic_with_no_messages() {
+        let stream = test_stream(2, BacklogPolicy::DropOldest);
+        assert!(stream.backlog_stats("untouched").is_none());
+    }
+
+    #[test]
+    fn application_score_composes_external_and_penalty_additively() {
+        let mut stream = test_stream(2, BacklogPolicy::DropOldest);
+        let peer_id = PeerId::random();
+
+        stream.set_application_score(&peer_id, 5.0);
+        assert_eq!(
+            combined_application_score(
+                &stream.external_application_scores,
+                &stream.subscription_penalties,
+                &peer_id
+            ),
+            5.0
+        );
+
+        // A penalty accrued by the subscription filter composes with the existing external
+        // score instead of overwriting it.
+        *stream.subscription_penalties.entry(peer_id).or_insert(0.0) -= 2.0;
+        assert_eq!(
+            combined_application_score(
+                &stream.external_application_scores,
+                &stream.subscription_penalties,
+                &peer_id
+            ),
+            3.0
+        );
+
+        // And a later external update composes with the existing penalty instead of the other
+        // way around.
+        stream.set_application_score(&peer_id, 10.0);
+        assert_eq!(
+            combined_application_score(
+                &stream.external_application_scores,
+                &stream.subscription_penalties,
+                &peer_id
+            ),
+            8.0
+        );
+    }
+
+    fn test_thresholds() -> PeerScoreThresholds {
+        PeerScoreThresholds {
+            gossip_threshold: -10.0,
+            publish_threshold: -50.0,
+            graylist_threshold: -80.0,
+            accept_px_threshold: 0.0,
+            opportunistic_graft_threshold: 0.0,
+        }
+    }
+
+    #[test]
+    fn score_transition_fires_once_per_crossing() {
+        let thresholds = test_thresholds();
+        let mut bands = HashMap::new();
+        let peer_id = PeerId::random();
+
+        // Above every threshold: no transition yet.
+        assert_eq!(record_score_band(&mut bands, &thresholds, peer_id, 0.0), None);
+
+        // Drops below the gossip threshold: exactly one transition.
+        assert_eq!(
+            record_score_band(&mut bands, &thresholds, peer_id, -20.0),
+            Some((peer_id, -20.0, ScoreThreshold::Gossip))
+        );
+
+        // Score stays in the same band on the next call: no repeated event.
+        assert_eq!(
+            record_score_band(&mut bands, &thresholds, peer_id, -25.0),
+            None
+        );
+
+        // Drops further, past the publish threshold: a second, distinct transition.
+        assert_eq!(
+            record_score_band(&mut bands, &thresholds, peer_id, -60.0),
+            Some((peer_id, -60.0, ScoreThreshold::Publish))
+        );
+    }
+}
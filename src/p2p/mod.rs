@@ -0,0 +1,5 @@
+pub mod dispersal;
+pub mod gossipsub;
+
+pub use dispersal::Dispersal;
+pub use gossipsub::GossipsubStream;
@@ -0,0 +1,80 @@
+//! `std::io::Write` / `futures::io::AsyncWrite` adapters over [`FileAdder`], so a `FileAdder` can
+//! be driven as an ordinary streaming sink (e.g. `std::io::copy(&mut reader, &mut writer)`)
+//! instead of callers sizing a reuse buffer by hand and looping `push` themselves.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use cid::Cid;
+
+use crate::file::adder::FileAdder;
+
+/// A block finished while writing into a [`FileAdderWriter`].
+pub type FinishedBlock = (Cid, Vec<u8>);
+
+/// An `std::io::Write` (and [`futures::io::AsyncWrite`]) sink over a [`FileAdder`]. Every write
+/// drives `FileAdder::push` on the given bytes directly and stashes any blocks it completes;
+/// callers drain them with [`Self::blocks`] as they go, or all at once via [`Self::finish`].
+pub struct FileAdderWriter {
+    adder: FileAdder,
+    finished: Vec<FinishedBlock>,
+}
+
+impl Default for FileAdderWriter {
+    fn default() -> Self {
+        Self::new(FileAdder::default())
+    }
+}
+
+impl FileAdderWriter {
+    /// Wraps an existing `adder` for streaming writes.
+    pub fn new(adder: FileAdder) -> Self {
+        FileAdderWriter {
+            adder,
+            finished: Vec::new(),
+        }
+    }
+
+    /// Drains the blocks completed so far without finishing the underlying file.
+    pub fn blocks(&mut self) -> impl Iterator<Item = FinishedBlock> + '_ {
+        self.finished.drain(..)
+    }
+
+    /// Finishes the file, returning every block completed by the final `push` together with any
+    /// already held back from earlier writes.
+    pub fn finish(mut self) -> Vec<FinishedBlock> {
+        self.finished.extend(self.adder.finish());
+        self.finished
+    }
+}
+
+impl io::Write for FileAdderWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let (blocks, consumed) = self.adder.push(buf);
+        self.finished.extend(blocks);
+        Ok(consumed)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl futures::io::AsyncWrite for FileAdderWriter {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _ctx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(io::Write::write(&mut *self, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _ctx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1 @@
+pub mod adder_writer;